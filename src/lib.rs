@@ -8,26 +8,156 @@ use embedded_hal::digital::v2::InputPin;
 use embedded_time::duration::*;
 use embedded_time::fixed_point::FixedPoint;
 pub use rotary_encoder_hal::Direction;
-use rotary_encoder_hal::Rotary;
 
 const DEGREES_PER_REV: u16 = 360;
 
 #[derive(Debug, Format, Copy, Clone)]
 pub enum Error {
     VelocityArithmeticOverflowWouldOccur,
+    /// Returned by [`Angle::sub`] (and anything built on it) when the two angles being
+    /// differenced come from encoders configured with different `counts_per_rev`.
+    MismatchedCountsPerRev,
+    /// Returned by [`Encoder::acceleration_rad_per_sec2`] / [`Encoder::acceleration_deg_per_sec2`]
+    /// until at least two well-formed velocity samples have been recorded.
+    InsufficientSamples,
 }
 
-pub struct Encoder<A, B>
+/// The classic quadrature transition table, indexed by `(previous_ab << 2) | current_ab`, where
+/// `ab` is the 2-bit reading of the A/B pins. Yields -1, 0, or +1 counts to apply per transition;
+/// an entry of `0` where both bits flipped at once (`previous_ab ^ current_ab == 0b11`) is an
+/// invalid transition that can only be explained by contact bounce or a missed sample.
+const STATES: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// How many valid quadrature transitions constitute one logical detent.
+#[derive(Debug, Format, Copy, Clone, PartialEq, Eq)]
+pub enum StepMode {
+    /// One detent per four valid transitions (the full quadrature cycle).
+    Full,
+    /// One detent per two valid transitions.
+    Half,
+    /// One detent per valid transition.
+    Quarter,
+}
+
+impl StepMode {
+    fn transitions_per_detent(&self) -> i8 {
+        match self {
+            StepMode::Full => 4,
+            StepMode::Half => 2,
+            StepMode::Quarter => 1,
+        }
+    }
+}
+
+/// Decodes quadrature A/B pin readings directly, without relying on an external decoder, and
+/// keeps a running count of invalid transitions for contact-bounce diagnostics.
+struct QuadratureDecoder {
+    previous_ab: u8,
+    step_mode: StepMode,
+    detent_accumulator: i8,
+    glitch_count: u32,
+}
+
+impl QuadratureDecoder {
+    fn new(step_mode: StepMode) -> Self {
+        QuadratureDecoder {
+            previous_ab: 0,
+            step_mode,
+            detent_accumulator: 0,
+            glitch_count: 0,
+        }
+    }
+
+    fn poll<A, B>(&mut self, pin_a: &A, pin_b: &B) -> Result<Direction, Either<A::Error, B::Error>>
+    where
+        A: InputPin,
+        B: InputPin,
+    {
+        let a = pin_a.is_high().map_err(Either::Left)?;
+        let b = pin_b.is_high().map_err(Either::Right)?;
+        let current_ab = ((a as u8) << 1) | (b as u8);
+
+        let index = ((self.previous_ab << 2) | current_ab) as usize;
+        let step = STATES[index];
+        if step == 0 && (self.previous_ab ^ current_ab) == 0b11 {
+            self.glitch_count += 1;
+        }
+        self.previous_ab = current_ab;
+
+        self.detent_accumulator += step;
+        let transitions_per_detent = self.step_mode.transitions_per_detent();
+        let direction = if self.detent_accumulator >= transitions_per_detent {
+            self.detent_accumulator -= transitions_per_detent;
+            Direction::CounterClockwise
+        } else if self.detent_accumulator <= -transitions_per_detent {
+            self.detent_accumulator += transitions_per_detent;
+            Direction::Clockwise
+        } else {
+            Direction::None
+        };
+
+        Ok(direction)
+    }
+}
+
+/// A fixed-size ring buffer of the most recent [`Velocity`] samples, used to derive angular
+/// acceleration without reimplementing sample bookkeeping outside the crate.
+#[derive(Clone, Copy, Debug)]
+struct VelocityHistory<const N: usize> {
+    samples: [Option<Velocity>; N],
+    next_index: usize,
+}
+
+impl<const N: usize> VelocityHistory<N> {
+    /// # Panics
+    ///
+    /// Panics if `N < 2`: reporting acceleration needs at least two samples, and the index
+    /// arithmetic in `last_two` relies on there being at least two slots to look back across.
+    fn new() -> Self {
+        assert!(N >= 2, "VelocityHistory requires at least 2 samples");
+        VelocityHistory {
+            samples: [None; N],
+            next_index: 0,
+        }
+    }
+
+    fn push(&mut self, sample: Velocity) {
+        self.samples[self.next_index] = Some(sample);
+        self.next_index = (self.next_index + 1) % N;
+    }
+
+    /// The two most recent well-formed samples, as `(newest, previous)`.
+    fn last_two(&self) -> Result<(Velocity, Velocity), Error> {
+        let newest_index = (self.next_index + N - 1) % N;
+        let previous_index = (self.next_index + N - 2) % N;
+        match (self.samples[newest_index], self.samples[previous_index]) {
+            (Some(newest), Some(previous)) => Ok((newest, previous)),
+            _ => Err(Error::InsufficientSamples),
+        }
+    }
+}
+
+/// Drives a quadrature rotary encoder's pins and tracks its angle, velocity, and acceleration.
+///
+/// `N` is the number of velocity samples kept for acceleration reporting and must be `>= 2`;
+/// constructing an `Encoder` with `N < 2` panics (see [`VelocityHistory::new`]).
+pub struct Encoder<A, B, const N: usize = 4>
 where
     A: InputPin,
     B: InputPin,
 {
-    hardware: Rotary<A, B>,
+    pin_a: A,
+    pin_b: B,
+    decoder: QuadratureDecoder,
     angle: Angle,
     velocity: Velocity,
+    velocity_history: VelocityHistory<N>,
+    scaled_velocity: ScaledVelocity,
+    delta_accumulator: i32,
+    last_capture_time_since_epoch_milli_sec: Milliseconds<u32>,
 }
 
-impl<A, B> Encoder<A, B>
+impl<A, B, const N: usize> Encoder<A, B, N>
 where
     A: InputPin,
     B: InputPin,
@@ -36,10 +166,11 @@ where
     pub fn new(
         pin_a: A,
         pin_b: B,
+        step_mode: StepMode,
+        scaled_velocity_config: ScaledVelocityConfig,
         starting_angle: Angle,
         initial_time_since_epoch_milli_sec: Milliseconds<u32>,
     ) -> Self {
-        let hardware = Rotary::new(pin_a, pin_b);
         let velocity = Velocity::new(
             initial_time_since_epoch_milli_sec,
             initial_time_since_epoch_milli_sec,
@@ -47,9 +178,15 @@ where
             starting_angle,
         );
         Encoder {
-            hardware,
+            pin_a,
+            pin_b,
+            decoder: QuadratureDecoder::new(step_mode),
             angle: starting_angle,
             velocity,
+            velocity_history: VelocityHistory::new(),
+            scaled_velocity: ScaledVelocity::new(scaled_velocity_config),
+            delta_accumulator: 0,
+            last_capture_time_since_epoch_milli_sec: initial_time_since_epoch_milli_sec,
         }
     }
 
@@ -59,16 +196,59 @@ where
         &mut self,
         current_time_since_epoch: Milliseconds<u32>,
     ) -> Result<Direction, Either<A::Error, B::Error>> {
-        let direction = self.hardware.update()?;
+        let direction = self.decoder.poll(&self.pin_a, &self.pin_b)?;
         self.angle.update(direction);
         self.velocity.update(self.angle, current_time_since_epoch);
+        self.velocity_history.push(self.velocity);
+        self.scaled_velocity.update(direction);
+        self.delta_accumulator += match direction {
+            Direction::CounterClockwise => 1,
+            Direction::Clockwise => -1,
+            Direction::None => 0,
+        };
         Ok(direction)
     }
 
-    /// Returns a mutible reference to the underlying hardware so one
-    /// can clear the interrupt pending bits of the rotary_encoder.
-    pub fn hardware(&mut self) -> &mut Rotary<A, B> {
-        &mut self.hardware
+    /// Returns mutable references to the underlying pins so one can clear the interrupt pending
+    /// bits of the rotary encoder.
+    pub fn pins(&mut self) -> (&mut A, &mut B) {
+        (&mut self.pin_a, &mut self.pin_b)
+    }
+
+    /// The number of invalid quadrature transitions (both A and B flipping between reads) seen so
+    /// far, a proxy for contact bounce or missed samples on a noisy mechanical encoder.
+    pub fn glitch_count(&self) -> u32 {
+        self.decoder.glitch_count
+    }
+
+    /// A helper function so there is not repetative code in acceleration_rad_per_sec2 and
+    /// acceleration_deg_per_sec2
+    fn velocity_history_diffs(&self) -> Result<(Velocity, Velocity, Milliseconds<u32>), Error> {
+        let (newest, previous) = self.velocity_history.last_two()?;
+        let delta_time = newest
+            .final_time_since_epoch()
+            .elapsed_since(previous.final_time_since_epoch())
+            .ok_or(Error::VelocityArithmeticOverflowWouldOccur)?;
+
+        Ok((newest, previous, delta_time))
+    }
+
+    /// Computes angular acceleration in rad/s^2 from the change in `radians_per_sec` over the
+    /// change in sample time between the two most recent well-formed velocity samples.
+    pub fn acceleration_rad_per_sec2(&self) -> Result<f32, Error> {
+        let (newest, previous, delta_time) = self.velocity_history_diffs()?;
+        let delta_rad_per_sec = newest.radians_per_sec()? - previous.radians_per_sec()?;
+
+        Ok(delta_rad_per_sec / delta_time.as_secs_f32())
+    }
+
+    /// Computes angular acceleration in deg/s^2 from the change in `degrees_per_sec` over the
+    /// change in sample time between the two most recent well-formed velocity samples.
+    pub fn acceleration_deg_per_sec2(&self) -> Result<f32, Error> {
+        let (newest, previous, delta_time) = self.velocity_history_diffs()?;
+        let delta_deg_per_sec = newest.degrees_per_sec()? - previous.degrees_per_sec()?;
+
+        Ok(delta_deg_per_sec / delta_time.as_secs_f32())
     }
 
     /// Gets the current angle of the rotary encoder
@@ -83,38 +263,165 @@ where
         current_angle: Angle,
         current_time_since_epoch_milli_sec: Milliseconds<u32>,
     ) -> Velocity {
-        self.velocity.final_time_since_epoch_milli_sec = current_time_since_epoch_milli_sec;
+        self.velocity.final_time_since_epoch = current_time_since_epoch_milli_sec;
         self.velocity.final_angle = current_angle;
         let calculated_velocity = self.velocity.clone();
         self.velocity
             .update(current_angle, current_time_since_epoch_milli_sec);
         return calculated_velocity;
     }
+
+    /// Gets the current normalized, linearly-decaying velocity of the rotary encoder.
+    ///
+    /// Unlike [`Encoder::velocity`], this does not depend on wall-clock time: it ramps up by a
+    /// configurable increment every time the encoder actually moves and decays by a configurable
+    /// decrement every time it is polled without having moved, giving callers like menu scrolling
+    /// or volume knobs an acceleration-like feel without floating-point time math.
+    pub fn scaled_velocity(&self) -> &ScaledVelocity {
+        &self.scaled_velocity
+    }
+
+    /// Overrides the ramp/decay rates used by [`Encoder::scaled_velocity`], preserving its
+    /// current magnitude and direction.
+    pub fn set_scaled_velocity_config(&mut self, config: ScaledVelocityConfig) {
+        self.scaled_velocity.set_config(config);
+    }
+
+    /// Atomically reports the current count, the delta accumulated since the previous call to
+    /// `capture`, and a frequency derived from that delta and the elapsed time, all from a single
+    /// read.
+    ///
+    /// Interrupt-driven calls to [`Encoder::update`] between captures accumulate into the delta
+    /// rather than being lost, unlike [`Encoder::velocity`], which mutates shared fields and can
+    /// race with `update`.
+    pub fn capture(&mut self, current_time_since_epoch_milli_sec: Milliseconds<u32>) -> Snapshot {
+        let delta = self.delta_accumulator;
+        self.delta_accumulator = 0;
+
+        let elapsed_milli_sec = current_time_since_epoch_milli_sec
+            .elapsed_since(self.last_capture_time_since_epoch_milli_sec)
+            .unwrap_or(Milliseconds(0_u32));
+        self.last_capture_time_since_epoch_milli_sec = current_time_since_epoch_milli_sec;
+
+        let frequency_hz = if elapsed_milli_sec == Milliseconds(0_u32) {
+            0.0
+        } else {
+            (delta as f32) / elapsed_milli_sec.as_secs_f32()
+        };
+
+        let counts_per_rev = self.angle.counts_per_rev;
+        let degrees_per_sec = frequency_hz * DEGREES_PER_REV as f32 / counts_per_rev as f32;
+        let radians_per_sec = degrees_per_sec * PI / 180.0;
+
+        Snapshot {
+            count: self.angle.total_counts(),
+            delta,
+            frequency_hz,
+            counts_per_rev,
+            degrees_per_sec,
+            radians_per_sec,
+        }
+    }
+}
+
+/// An atomic, self-resetting measurement window produced by [`Encoder::capture`].
+#[derive(Debug, Format, Copy, Clone)]
+pub struct Snapshot {
+    /// The absolute count of the encoder at the time of capture, accounting for every revolution
+    /// tracked so far (see [`Angle::turns`]), not just the within-revolution count.
+    pub count: i64,
+    /// The counts accumulated since the previous capture.
+    pub delta: i32,
+    /// Counts-per-second, computed from `delta` and the elapsed time since the previous capture.
+    pub frequency_hz: f32,
+    /// The configured counts-per-revolution of the encoder this snapshot came from.
+    pub counts_per_rev: u16,
+    pub degrees_per_sec: f32,
+    pub radians_per_sec: f32,
+}
+
+/// A duration type `Velocity` can use as its time base: any fixed-point `embedded_time` duration
+/// whose underlying integer tick counter may wrap (e.g. a free-running millisecond or
+/// microsecond monotonic clock).
+pub trait TimeBase: FixedPoint + Copy + PartialOrd {
+    /// Computes `self - earlier`, treating a single numeric wrap of the underlying integer tick
+    /// counter as a normal, non-error elapsed duration instead of refusing it outright. Only
+    /// returns `None` if the wrapped delta is itself zero (i.e. no time could plausibly have
+    /// elapsed).
+    fn elapsed_since(self, earlier: Self) -> Option<Self>;
+
+    /// This duration expressed in seconds, for use in per-second rate calculations.
+    fn as_secs_f32(self) -> f32;
+}
+
+impl TimeBase for Milliseconds<u32> {
+    fn elapsed_since(self, earlier: Self) -> Option<Self> {
+        if self >= earlier {
+            Some(self - earlier)
+        } else {
+            // the underlying u32 tick counter wrapped exactly once between samples
+            let wrapped = (Milliseconds(u32::MAX) - earlier) + self + Milliseconds(1_u32);
+            if wrapped == Milliseconds(0_u32) {
+                None
+            } else {
+                Some(wrapped)
+            }
+        }
+    }
+
+    fn as_secs_f32(self) -> f32 {
+        *self.integer() as f32 / 1_000.0
+    }
+}
+
+impl TimeBase for Microseconds<u32> {
+    fn elapsed_since(self, earlier: Self) -> Option<Self> {
+        if self >= earlier {
+            Some(self - earlier)
+        } else {
+            // the underlying u32 tick counter wrapped exactly once between samples
+            let wrapped = (Microseconds(u32::MAX) - earlier) + self + Microseconds(1_u32);
+            if wrapped == Microseconds(0_u32) {
+                None
+            } else {
+                Some(wrapped)
+            }
+        }
+    }
+
+    fn as_secs_f32(self) -> f32 {
+        *self.integer() as f32 / 1_000_000.0
+    }
 }
 
-// todo: Make the time requirement less restrictive
 #[derive(Clone, Copy, Debug)]
-pub struct Velocity {
-    initial_time_since_epoch_milli_sec: Milliseconds<u32>,
-    final_time_since_epoch_milli_sec: Milliseconds<u32>,
+pub struct Velocity<T = Milliseconds<u32>>
+where
+    T: TimeBase,
+{
+    initial_time_since_epoch: T,
+    final_time_since_epoch: T,
     initial_angle: Angle,
     final_angle: Angle,
 }
 
 // For nice debugging
-impl defmt::Format for Velocity {
+impl<T> defmt::Format for Velocity<T>
+where
+    T: TimeBase,
+{
     fn format(&self, fmt: defmt::Formatter) {
         defmt::write!(
             fmt,
-            r#"initial_time_since_epoch_milli_sec = {}
-final_time_since_epoch_milli_sec = {}
+            r#"initial_time_since_epoch_secs = {}
+final_time_since_epoch_secs = {}
 initial_angle_deg = {}
 final_angle_deg = {}
 degrees_per_sec = {}
 rad_per_sec = {}
 "#,
-            self.initial_time_since_epoch_milli_sec.integer(),
-            self.final_time_since_epoch_milli_sec.integer(),
+            self.initial_time_since_epoch.as_secs_f32(),
+            self.final_time_since_epoch.as_secs_f32(),
             self.initial_angle.degrees(),
             self.final_angle.degrees(),
             self.degrees_per_sec().unwrap_or(f32::NAN),
@@ -123,57 +430,52 @@ rad_per_sec = {}
     }
 }
 
-impl Velocity {
+impl<T> Velocity<T>
+where
+    T: TimeBase,
+{
     pub fn new(
-        initial_time_since_epoch_milli_sec: Milliseconds<u32>,
-        final_time_since_epoch_milli_sec: Milliseconds<u32>,
+        initial_time_since_epoch: T,
+        final_time_since_epoch: T,
         initial_angle: Angle,
         final_angle: Angle,
     ) -> Self {
         Velocity {
-            initial_time_since_epoch_milli_sec,
-            final_time_since_epoch_milli_sec,
+            initial_time_since_epoch,
+            final_time_since_epoch,
             initial_angle,
             final_angle,
         }
     }
 
-    fn update(
-        &mut self,
-        current_angle: Angle,
-        current_time_since_epoch_milli_sec: Milliseconds<u32>,
-    ) {
+    fn update(&mut self, current_angle: Angle, current_time_since_epoch: T) {
         self.initial_angle = self.final_angle;
         self.final_angle = current_angle;
-        self.initial_time_since_epoch_milli_sec = self.final_time_since_epoch_milli_sec;
-        self.final_time_since_epoch_milli_sec = current_time_since_epoch_milli_sec;
+        self.initial_time_since_epoch = self.final_time_since_epoch;
+        self.final_time_since_epoch = current_time_since_epoch;
     }
 
     /// A helper function so there is not repetative code in radians_per_sec and degrees_per_sec
-    fn angle_time_diffs(&self) -> (Angle, Result<Milliseconds<u32>, Error>) {
-        let delta_angle = self.final_angle - self.initial_angle;
-        if self.final_time_since_epoch_milli_sec < self.initial_time_since_epoch_milli_sec {
-            return (
-                delta_angle,
-                Err(Error::VelocityArithmeticOverflowWouldOccur),
-            );
-        }
-        let delta_time_milli_sec =
-            self.final_time_since_epoch_milli_sec - self.initial_time_since_epoch_milli_sec;
-
-        (delta_angle, Ok(delta_time_milli_sec))
+    fn angle_time_diffs(&self) -> Result<(Angle, T), Error> {
+        let delta_angle = (self.final_angle - self.initial_angle)?;
+        let delta_time = self
+            .final_time_since_epoch
+            .elapsed_since(self.initial_time_since_epoch)
+            .ok_or(Error::VelocityArithmeticOverflowWouldOccur)?;
+
+        Ok((delta_angle, delta_time))
     }
 
     /// This function exists so that the caller can reconstuct a velocity when a potetial
     /// arithmetic overflow is detected
-    pub fn initial_time_since_epoch_milli_sec(&self) -> Milliseconds<u32> {
-        self.initial_time_since_epoch_milli_sec
+    pub fn initial_time_since_epoch(&self) -> T {
+        self.initial_time_since_epoch
     }
 
     /// This function exists so that the caller can reconstuct a velocity when a potetial
     /// arithmetic overflow is detected
-    pub fn final_time_since_epoch_milli_sec(&self) -> Milliseconds<u32> {
-        self.final_time_since_epoch_milli_sec
+    pub fn final_time_since_epoch(&self) -> T {
+        self.final_time_since_epoch
     }
 
     /// This function exists so that the caller can reconstuct a velocity when a potetial
@@ -189,38 +491,135 @@ impl Velocity {
     }
 
     pub fn radians_per_sec(&self) -> Result<f32, Error> {
-        let (delta_angle, delta_time_milli_sec) = self.angle_time_diffs();
-        let delta_time_milli_sec = delta_time_milli_sec?;
+        let (delta_angle, delta_time) = self.angle_time_diffs()?;
 
-        Ok(delta_angle.radians() / ((*delta_time_milli_sec.integer() as f32) / 1_000.0))
+        Ok(delta_angle.total_radians() / delta_time.as_secs_f32())
     }
 
     pub fn degrees_per_sec(&self) -> Result<f32, Error> {
-        let (delta_angle, delta_time_milli_sec) = self.angle_time_diffs();
-        let delta_time_milli_sec = delta_time_milli_sec?;
-        Ok(delta_angle.degrees() / ((*delta_time_milli_sec.integer() as f32) / 1_000.0))
+        let (delta_angle, delta_time) = self.angle_time_diffs()?;
+        Ok(delta_angle.total_degrees() / delta_time.as_secs_f32())
+    }
+}
+
+/// Tuning knobs for [`ScaledVelocity`]'s ramp up/decay rates.
+#[derive(Debug, Format, Copy, Clone)]
+pub struct ScaledVelocityConfig {
+    /// Added to the stored magnitude each time the encoder moves, clamped to `1.0`.
+    pub increment: f32,
+    /// Subtracted from the stored magnitude each time the encoder is polled without moving,
+    /// clamped to `0.0`.
+    pub decrement: f32,
+}
+
+impl Default for ScaledVelocityConfig {
+    fn default() -> Self {
+        ScaledVelocityConfig {
+            increment: 0.2,
+            decrement: 0.01,
+        }
+    }
+}
+
+/// A normalized, linearly-decaying velocity for UI use (menu scrolling, volume knobs, etc.)
+/// that gives an acceleration-like "the faster you spin, the faster it moves" feel without
+/// relying on wall-clock time.
+///
+/// The magnitude is held in `[0.0, 1.0]`: it increases by [`ScaledVelocityConfig::increment`]
+/// every time [`Encoder::update`] observes a non-[`Direction::None`] direction, and decreases by
+/// [`ScaledVelocityConfig::decrement`] every time it does not.
+#[derive(Debug, Copy, Clone)]
+pub struct ScaledVelocity {
+    magnitude: f32,
+    direction: Direction,
+    config: ScaledVelocityConfig,
+}
+
+// For nice debugging
+impl defmt::Format for ScaledVelocity {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            r#"magnitude = {}
+signed = {}
+"#,
+            self.magnitude,
+            self.signed(),
+        )
+    }
+}
+
+impl ScaledVelocity {
+    pub fn new(config: ScaledVelocityConfig) -> Self {
+        ScaledVelocity {
+            magnitude: 0.0,
+            direction: Direction::None,
+            config,
+        }
+    }
+
+    /// Overrides the ramp/decay rates, preserving the current magnitude and direction.
+    pub fn set_config(&mut self, config: ScaledVelocityConfig) {
+        self.config = config;
+    }
+
+    fn update(&mut self, direction: Direction) {
+        match direction {
+            Direction::None => self.magnitude = (self.magnitude - self.config.decrement).max(0.0),
+            _ => {
+                self.direction = direction;
+                self.magnitude = (self.magnitude + self.config.increment).min(1.0);
+            }
+        }
+    }
+
+    /// The raw magnitude of the velocity, in `[0.0, 1.0]`.
+    pub fn magnitude(&self) -> f32 {
+        self.magnitude
+    }
+
+    /// The direction of the last non-[`Direction::None`] movement seen.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The magnitude signed by the direction of the last movement: positive for
+    /// [`Direction::CounterClockwise`], negative for [`Direction::Clockwise`], and `0.0` if the
+    /// encoder has never moved.
+    pub fn signed(&self) -> f32 {
+        match self.direction {
+            Direction::CounterClockwise => self.magnitude,
+            Direction::Clockwise => -self.magnitude,
+            Direction::None => 0.0,
+        }
     }
 }
 
 #[derive(Clone, Copy, Debug, Format)]
 pub struct Angle {
-    /// counts of the rotary encoder
+    /// counts within the current revolution, always in `[0, counts_per_rev)`
     counts: i16,
+    /// How many complete revolutions away from the origin, positive for counter-clockwise
+    revolutions: i32,
     /// How many counts there are for a the rotary_encoder
     counts_per_rev: u16,
 }
 
 impl Sub for Angle {
-    type Output = Self;
+    /// Differencing two angles can fail if they come from encoders configured with different
+    /// `counts_per_rev`, so this returns a `Result` instead of panicking, which matters in a
+    /// `no_std` firmware context.
+    type Output = Result<Self, Error>;
 
     fn sub(self, other: Self) -> Self::Output {
-        // todo: remove this assertion and return a result instead
-        assert!(self.counts_per_rev == other.counts_per_rev);
-
-        Self {
-            counts: self.counts - other.counts,
-            counts_per_rev: self.counts_per_rev,
+        if self.counts_per_rev != other.counts_per_rev {
+            return Err(Error::MismatchedCountsPerRev);
         }
+
+        Ok(Self::from_total_counts(
+            self.total_counts() - other.total_counts(),
+            self.counts_per_rev,
+        ))
     }
 }
 
@@ -228,27 +627,54 @@ impl Angle {
     /// Creates a angle type given the maximum counts per revolutions and how far, in counts,
     /// the the physical location of the rotary encoders position is displaced from the origin.
     pub fn new(counts_per_rev: u16, origin_offset_counts: i16) -> Self {
+        Self::from_total_counts(origin_offset_counts as i64, counts_per_rev)
+    }
+
+    fn total_counts(&self) -> i64 {
+        self.revolutions as i64 * self.counts_per_rev as i64 + self.counts as i64
+    }
+
+    fn from_total_counts(total_counts: i64, counts_per_rev: u16) -> Self {
+        let counts_per_rev_i64 = counts_per_rev as i64;
+        let revolutions = total_counts.div_euclid(counts_per_rev_i64);
+        let counts = total_counts.rem_euclid(counts_per_rev_i64);
         Angle {
-            counts: origin_offset_counts,
+            counts: counts as i16,
+            revolutions: revolutions as i32,
             counts_per_rev,
         }
     }
 
-    /// Increments or decrements the counter depending on the direction
+    /// Increments or decrements the counter depending on the direction, carrying into
+    /// `revolutions` whenever the within-revolution count wraps through zero.
     fn update(&mut self, direction: Direction) {
         match direction {
-            Direction::CounterClockwise => self.counts += 1,
-            Direction::Clockwise => self.counts -= 1,
+            Direction::CounterClockwise => {
+                self.counts += 1;
+                if self.counts == self.counts_per_rev as i16 {
+                    self.counts = 0;
+                    self.revolutions += 1;
+                }
+            }
+            Direction::Clockwise => {
+                self.counts -= 1;
+                if self.counts < 0 {
+                    self.counts = self.counts_per_rev as i16 - 1;
+                    self.revolutions -= 1;
+                }
+            }
             Direction::None => (),
         };
     }
 
-    /// Gets the angle of the encoder in radians
+    /// Gets the angle of the encoder within the current revolution, in radians, in `[0, 2π)`;
+    /// see [`Angle::total_radians`] for the unbounded angle.
     pub fn radians(&self) -> f32 {
         self.degrees() * PI / 180.0
     }
 
-    /// Gets the angle of the encoder in degrees.
+    /// Gets the angle of the encoder within the current revolution, in degrees, in `[0, 360)`;
+    /// see [`Angle::total_degrees`] for the unbounded angle.
     pub fn degrees(&self) -> f32 {
         let counts = self.counts as f32;
         let degrees_per_rev = DEGREES_PER_REV as f32;
@@ -256,11 +682,230 @@ impl Angle {
 
         counts * degrees_per_rev / counts_per_rev
     }
+
+    /// Gets the unbounded angle of the encoder in degrees, accounting for every revolution
+    /// tracked so far. Unlike [`Angle::degrees`], this keeps growing (or shrinking) across
+    /// continuous rotation instead of wrapping every revolution.
+    pub fn total_degrees(&self) -> f32 {
+        let total_counts = self.total_counts() as f32;
+        let degrees_per_rev = DEGREES_PER_REV as f32;
+        let counts_per_rev = self.counts_per_rev as f32;
+
+        total_counts * degrees_per_rev / counts_per_rev
+    }
+
+    /// Gets the unbounded angle of the encoder in radians, accounting for every revolution
+    /// tracked so far.
+    pub fn total_radians(&self) -> f32 {
+        self.total_degrees() * PI / 180.0
+    }
+
+    /// Gets the angle of the encoder in degrees, normalized to `[0, 360)`.
+    pub fn normalized_degrees(&self) -> f32 {
+        let degrees = self.degrees();
+        if degrees < 0.0 {
+            degrees + DEGREES_PER_REV as f32
+        } else {
+            degrees
+        }
+    }
+
+    /// The number of complete revolutions away from the origin, positive for counter-clockwise.
+    pub fn turns(&self) -> i32 {
+        self.revolutions
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// A settable `InputPin` so encoder tests can drive quadrature transitions directly, without
+    /// real hardware.
+    #[derive(Clone)]
+    struct MockPin(Rc<Cell<bool>>);
+
+    impl MockPin {
+        fn new(initial_high: bool) -> Self {
+            MockPin(Rc::new(Cell::new(initial_high)))
+        }
+
+        fn set(&self, high: bool) {
+            self.0.set(high);
+        }
+    }
+
+    impl InputPin for MockPin {
+        type Error = core::convert::Infallible;
+
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            Ok(self.0.get())
+        }
+
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            Ok(!self.0.get())
+        }
+    }
+
+    /// Sets the A/B pins to the 2-bit reading `ab` (bit 1 is A, bit 0 is B).
+    fn set_ab(pin_a: &MockPin, pin_b: &MockPin, ab: u8) {
+        pin_a.set(ab & 0b10 != 0);
+        pin_b.set(ab & 0b01 != 0);
+    }
+
+    #[test]
+    fn capture_reports_total_count_delta_and_frequency() {
+        let pin_a = MockPin::new(false);
+        let pin_b = MockPin::new(false);
+        let mut encoder = Encoder::<MockPin, MockPin>::new(
+            pin_a.clone(),
+            pin_b.clone(),
+            StepMode::Full,
+            ScaledVelocityConfig::default(),
+            Angle::new(600, 0),
+            Milliseconds(0_u32),
+        );
+
+        // One full CCW detent (4 valid transitions) under StepMode::Full.
+        let mut time_ms = 0_u32;
+        let mut last_direction = Direction::None;
+        for ab in [2, 3, 1, 0] {
+            set_ab(&pin_a, &pin_b, ab);
+            time_ms += 1;
+            last_direction = encoder.update(Milliseconds(time_ms)).unwrap();
+        }
+        assert_eq!(last_direction, Direction::CounterClockwise);
+
+        let snapshot = encoder.capture(Milliseconds(time_ms));
+        assert_eq!(snapshot.count, 1);
+        assert_eq!(snapshot.delta, 1);
+        // 1 count over 4 ms == 250 Hz
+        assert!((snapshot.frequency_hz - 250.0).abs() < 0.1);
+
+        // the delta accumulator resets after a capture
+        let next_snapshot = encoder.capture(Milliseconds(time_ms + 10));
+        assert_eq!(next_snapshot.delta, 0);
+    }
+
+    #[test]
+    fn step_mode_quarter_fires_a_direction_on_every_valid_transition() {
+        let pin_a = MockPin::new(false);
+        let pin_b = MockPin::new(false);
+        let mut encoder = Encoder::<MockPin, MockPin>::new(
+            pin_a.clone(),
+            pin_b.clone(),
+            StepMode::Quarter,
+            ScaledVelocityConfig::default(),
+            Angle::new(600, 0),
+            Milliseconds(0_u32),
+        );
+
+        let mut time_ms = 0_u32;
+        for ab in [2, 3, 1, 0] {
+            set_ab(&pin_a, &pin_b, ab);
+            time_ms += 1;
+            let direction = encoder.update(Milliseconds(time_ms)).unwrap();
+            assert_eq!(direction, Direction::CounterClockwise);
+        }
+    }
+
+    #[test]
+    fn invalid_quadrature_transition_is_counted_as_a_glitch() {
+        let pin_a = MockPin::new(false);
+        let pin_b = MockPin::new(false);
+        let mut encoder = Encoder::<MockPin, MockPin>::new(
+            pin_a.clone(),
+            pin_b.clone(),
+            StepMode::Full,
+            ScaledVelocityConfig::default(),
+            Angle::new(600, 0),
+            Milliseconds(0_u32),
+        );
+
+        assert_eq!(encoder.glitch_count(), 0);
+
+        // both bits flipping at once (00 -> 11) can only be contact bounce or a missed sample
+        set_ab(&pin_a, &pin_b, 3);
+        let direction = encoder.update(Milliseconds(1_u32)).unwrap();
+
+        assert_eq!(direction, Direction::None);
+        assert_eq!(encoder.glitch_count(), 1);
+    }
+
+    #[test]
+    fn acceleration_is_insufficient_samples_until_two_updates_then_reflects_slowdown() {
+        let pin_a = MockPin::new(false);
+        let pin_b = MockPin::new(false);
+        let mut encoder = Encoder::<MockPin, MockPin>::new(
+            pin_a.clone(),
+            pin_b.clone(),
+            StepMode::Quarter,
+            ScaledVelocityConfig::default(),
+            Angle::new(600, 0),
+            Milliseconds(0_u32),
+        );
+
+        assert!(matches!(
+            encoder.acceleration_rad_per_sec2(),
+            Err(Error::InsufficientSamples)
+        ));
+
+        set_ab(&pin_a, &pin_b, 2);
+        encoder.update(Milliseconds(1_u32)).unwrap();
+        assert!(matches!(
+            encoder.acceleration_deg_per_sec2(),
+            Err(Error::InsufficientSamples)
+        ));
+
+        // a second, equally fast detent: roughly constant speed
+        set_ab(&pin_a, &pin_b, 3);
+        encoder.update(Milliseconds(2_u32)).unwrap();
+        assert!(encoder.acceleration_rad_per_sec2().is_ok());
+
+        // a third detent spread over a much longer time: the encoder is slowing down
+        set_ab(&pin_a, &pin_b, 1);
+        encoder.update(Milliseconds(12_u32)).unwrap();
+
+        assert!(encoder.acceleration_rad_per_sec2().unwrap() < 0.0);
+        assert!(encoder.acceleration_deg_per_sec2().unwrap() < 0.0);
+    }
+
+    #[test]
+    fn scaled_velocity_ramps_decays_clamps_and_signs_correctly() {
+        let mut scaled_velocity = ScaledVelocity::new(ScaledVelocityConfig {
+            increment: 0.2,
+            decrement: 0.01,
+        });
+
+        scaled_velocity.update(Direction::CounterClockwise);
+        assert!((scaled_velocity.magnitude() - 0.2).abs() < f32::EPSILON);
+        assert!((scaled_velocity.signed() - 0.2).abs() < f32::EPSILON);
+
+        // clamps to 1.0 no matter how many times it moves in the same direction
+        for _ in 0..10 {
+            scaled_velocity.update(Direction::CounterClockwise);
+        }
+        assert!((scaled_velocity.magnitude() - 1.0).abs() < f32::EPSILON);
+
+        // a move the other way flips the sign and still ramps the magnitude up
+        scaled_velocity.update(Direction::Clockwise);
+        assert!((scaled_velocity.magnitude() - 1.0).abs() < f32::EPSILON);
+        assert!(scaled_velocity.signed() < 0.0);
+
+        // polling with no movement decays the magnitude, and clamps to 0.0
+        let mut scaled_velocity = ScaledVelocity::new(ScaledVelocityConfig {
+            increment: 0.2,
+            decrement: 0.01,
+        });
+        scaled_velocity.update(Direction::CounterClockwise);
+        for _ in 0..100 {
+            scaled_velocity.update(Direction::None);
+        }
+        assert_eq!(scaled_velocity.magnitude(), 0.0);
+        assert_eq!(scaled_velocity.signed(), 0.0);
+    }
 
     #[test]
     fn correct_angle_rotated_back_to_origin_deg() {
@@ -308,4 +953,68 @@ mod tests {
         eprintln!("velocity.radians_per_sec() = {}", velocity_radians_per_sec);
         assert!(velocity_radians_per_sec < (PI + 0.01) && velocity_radians_per_sec > (PI - 0.01))
     }
+
+    #[test]
+    fn velocity_survives_a_single_timestamp_wrap() {
+        let counts_per_rev = 2400;
+        let initial_angle = Angle::new(counts_per_rev, 0);
+        let angle_180_deg = Angle::new(counts_per_rev, (counts_per_rev / 2) as i16);
+
+        // 10 ms before wrapping, then 1 ms after: the monotonic clock wrapped through u32::MAX
+        // once, which should read as an 11 ms elapsed duration rather than an error.
+        let initial_time = Milliseconds(u32::MAX - 9);
+        let final_time = Milliseconds(1_u32);
+        let mut velocity = Velocity::new(initial_time, initial_time, initial_angle, initial_angle);
+
+        velocity.update(angle_180_deg, final_time);
+
+        let degrees_per_sec = velocity.degrees_per_sec().unwrap();
+        let expected_degrees_per_sec = 180.0 / (11.0 / 1_000.0);
+        assert!((degrees_per_sec - expected_degrees_per_sec).abs() < 1.0);
+    }
+
+    #[test]
+    fn multi_turn_angle_tracks_revolutions_past_one_rotation() {
+        let counts_per_rev = 600;
+        let mut angle = Angle::new(counts_per_rev, 0);
+        let direction = Direction::CounterClockwise;
+
+        for _ in 0..(counts_per_rev as usize * 2 + counts_per_rev as usize / 2) {
+            angle.update(direction);
+        }
+
+        assert_eq!(angle.turns(), 2);
+        assert!(angle.normalized_degrees() > 179.9 && angle.normalized_degrees() < 180.1);
+        assert!(angle.total_degrees() > 899.9 && angle.total_degrees() < 900.1);
+    }
+
+    #[test]
+    fn velocity_history_reports_insufficient_samples_until_two_are_pushed() {
+        let mut history: VelocityHistory<4> = VelocityHistory::new();
+        let counts_per_rev = 600;
+        let angle = Angle::new(counts_per_rev, 0);
+        let velocity = Velocity::new(Milliseconds(0_u32), Milliseconds(1_u32), angle, angle);
+
+        assert!(matches!(
+            history.last_two(),
+            Err(Error::InsufficientSamples)
+        ));
+
+        history.push(velocity);
+        assert!(matches!(
+            history.last_two(),
+            Err(Error::InsufficientSamples)
+        ));
+
+        history.push(velocity);
+        assert!(history.last_two().is_ok());
+    }
+
+    #[test]
+    fn angle_sub_errors_on_mismatched_counts_per_rev() {
+        let a = Angle::new(600, 0);
+        let b = Angle::new(2400, 0);
+
+        assert!(matches!(a - b, Err(Error::MismatchedCountsPerRev)));
+    }
 }